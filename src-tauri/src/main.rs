@@ -1,11 +1,14 @@
 use active_win_pos_rs::get_active_window;
-use rusqlite::{Connection, params};
-use std::{thread, time::Duration, path::PathBuf};
+use rusqlite::{Connection, params, params_from_iter, types::Value};
+use std::{thread, time::Duration, path::PathBuf, sync::Mutex};
 use chrono::{Utc, Local, NaiveDate, Datelike};
 use tauri::Manager;
 use serde::{Serialize, Deserialize};
 use anyhow::{Result, Context};
 
+// Single mutex-guarded connection shared across commands via Tauri state.
+struct Db(Mutex<Connection>);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[allow(dead_code)]
 struct Event {
@@ -24,6 +27,12 @@ struct AppStats {
     last_seen: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct TimeBreakdown {
+    app: String,
+    total_secs: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SearchResult {
     id: i64,
@@ -33,12 +42,43 @@ struct SearchResult {
     tags: Option<String>,
 }
 
+// Results plus a warning set when the query couldn't be parsed and was degraded to a literal scan.
+#[derive(Debug, Serialize, Deserialize)]
+struct SearchResponse {
+    results: Vec<SearchResult>,
+    warning: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 struct Config {
     blacklist: Vec<String>,
 }
 
+// Matching strategy for search_memories.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+enum SearchMode {
+    Exact,
+    Prefix,
+    Fuzzy,
+    FullText,
+}
+
+// Composable search filters; unset filters add no WHERE clause, and an unset limit defaults to 100.
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct SearchFilters {
+    app: Option<String>,
+    exclude_app: Option<String>,
+    title_contains: Option<String>,
+    tag: Option<String>,
+    before: Option<String>,
+    after: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    #[serde(default)]
+    reverse: bool,
+}
+
 fn get_db_path(app: &tauri::AppHandle) -> Result<PathBuf> {
     let mut path = app
         .path()
@@ -52,81 +92,94 @@ fn get_db_path(app: &tauri::AppHandle) -> Result<PathBuf> {
     Ok(path)
 }
 
-fn init_db(app: &tauri::AppHandle) -> Result<Connection> {
+fn open_connection(app: &tauri::AppHandle) -> Result<Connection> {
     let path = get_db_path(app)?;
     let conn = Connection::open(path)
         .context("Failed to open database")?;
 
-    // Main events table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS events (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            timestamp TEXT NOT NULL,
-            app TEXT NOT NULL,
-            title TEXT NOT NULL,
-            tags TEXT
-        )",
-        [],
-    ).context("Failed to create events table")?;
-
-    // Migration: Add tags column if it doesn't exist
-    conn.execute(
-        "ALTER TABLE events ADD COLUMN tags TEXT",
-        [],
-    ).ok(); // Ignore error if column already exists
-
-    // FTS5 virtual table for full-text search
-    conn.execute(
-        "CREATE VIRTUAL TABLE IF NOT EXISTS events_fts USING fts5(
-            app, title, content='events', content_rowid='id'
-        )",
-        [],
-    ).context("Failed to create FTS table")?;
-
-    // Triggers to keep FTS in sync
-    conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS events_ai AFTER INSERT ON events BEGIN
-            INSERT INTO events_fts(rowid, app, title) VALUES (new.id, new.app, new.title);
-        END",
-        [],
-    ).ok();
-
-    conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS events_ad AFTER DELETE ON events BEGIN
-            INSERT INTO events_fts(events_fts, rowid, app, title) VALUES('delete', old.id, old.app, old.title);
-        END",
-        [],
-    ).ok();
+    // Let readers and the writer coexist instead of failing fast with SQLITE_BUSY.
+    conn.busy_timeout(Duration::from_secs(5))
+        .context("Failed to set busy_timeout")?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .context("Failed to set journal_mode")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .context("Failed to set synchronous")?;
+    conn.pragma_update(None, "mmap_size", 268_435_456i64)
+        .context("Failed to set mmap_size")?;
 
-    conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS events_au AFTER UPDATE ON events BEGIN
-            INSERT INTO events_fts(events_fts, rowid, app, title) VALUES('delete', old.id, old.app, old.title);
-            INSERT INTO events_fts(rowid, app, title) VALUES (new.id, new.app, new.title);
-        END",
-        [],
-    ).ok();
+    Ok(conn)
+}
 
-    // Config table for blacklist
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS config (
-            key TEXT PRIMARY KEY,
-            value TEXT NOT NULL
-        )",
-        [],
-    ).context("Failed to create config table")?;
+// A versioned schema step, applied in its own transaction.
+struct Migration {
+    version: i64,
+    sql: &'static str,
+}
 
-    // Index for faster queries
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_timestamp ON events(timestamp)",
-        [],
-    ).ok();
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        sql: "
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                app TEXT NOT NULL,
+                title TEXT NOT NULL,
+                tags TEXT
+            );
+
+            CREATE VIRTUAL TABLE IF NOT EXISTS events_fts USING fts5(
+                app, title, content='events', content_rowid='id'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS events_ai AFTER INSERT ON events BEGIN
+                INSERT INTO events_fts(rowid, app, title) VALUES (new.id, new.app, new.title);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS events_ad AFTER DELETE ON events BEGIN
+                INSERT INTO events_fts(events_fts, rowid, app, title) VALUES('delete', old.id, old.app, old.title);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS events_au AFTER UPDATE ON events BEGIN
+                INSERT INTO events_fts(events_fts, rowid, app, title) VALUES('delete', old.id, old.app, old.title);
+                INSERT INTO events_fts(rowid, app, title) VALUES (new.id, new.app, new.title);
+            END;
+
+            CREATE TABLE IF NOT EXISTS config (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_timestamp ON events(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_app ON events(app);
+        ",
+    },
+    Migration {
+        version: 2,
+        sql: "ALTER TABLE events ADD COLUMN duration_secs INTEGER;",
+    },
+];
+
+fn run_migrations(conn: &mut Connection) -> Result<()> {
+    let current_version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .context("Failed to read user_version")?;
+
+    for migration in MIGRATIONS {
+        if migration.version <= current_version {
+            continue;
+        }
 
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_app ON events(app)",
-        [],
-    ).ok();
+        let tx = conn.transaction().context("Failed to start migration transaction")?;
+        tx.execute_batch(migration.sql)
+            .with_context(|| format!("Migration {} failed", migration.version))?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .with_context(|| format!("Failed to bump user_version to {}", migration.version))?;
+        tx.commit()
+            .with_context(|| format!("Failed to commit migration {}", migration.version))?;
+    }
 
-    Ok(conn)
+    Ok(())
 }
 
 fn load_blacklist(conn: &Connection) -> Vec<String> {
@@ -140,43 +193,100 @@ fn load_blacklist(conn: &Connection) -> Vec<String> {
     .unwrap_or_default()
 }
 
+// Gap, in seconds, after which a focus change counts as AFK rather than dwell time.
+const DEFAULT_IDLE_THRESHOLD_SECS: i64 = 300;
+
+fn load_idle_threshold_secs(conn: &Connection) -> i64 {
+    conn.query_row(
+        "SELECT value FROM config WHERE key = 'idle_threshold_secs'",
+        [],
+        |row| row.get::<_, String>(0)
+    )
+    .ok()
+    .and_then(|v| v.parse::<i64>().ok())
+    .unwrap_or(DEFAULT_IDLE_THRESHOLD_SECS)
+}
+
+// Seconds a window was actually dwelt on between two events, capped at
+// idle_threshold_secs so an AFK gap isn't attributed to whatever was focused.
+fn dwell_secs(prev: chrono::DateTime<Utc>, now: chrono::DateTime<Utc>, idle_threshold_secs: i64) -> i64 {
+    (now - prev).num_seconds().clamp(0, idle_threshold_secs)
+}
+
 fn start_logger(app: tauri::AppHandle) {
     thread::spawn(move || {
-        let conn = match init_db(&app) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("Failed to init DB: {}", e);
-                return;
-            }
-        };
+        let db = app.state::<Db>();
 
         let mut last_app = String::new();
         let mut last_title = String::new();
+        let mut last_event: Option<(i64, chrono::DateTime<Utc>)> = None;
 
         loop {
-            let blacklist = load_blacklist(&conn);
-
+            // Do the blocking OS call and wait before touching the shared connection,
+            // so a 2-second poll doesn't serialize every other command behind it.
             if let Ok(win) = get_active_window() {
                 let app_name = win.app_name;
                 let title = win.title;
 
+                let conn = match db.0.lock() {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        // A poisoned mutex would otherwise panic this thread on every
+                        // subsequent tick, silently killing background tracking forever.
+                        eprintln!("logger: shared connection lock poisoned, skipping tick: {e}");
+                        thread::sleep(Duration::from_secs(2));
+                        continue;
+                    }
+                };
+                let blacklist = load_blacklist(&conn);
+                // Floor defensively: a negative value shouldn't reach config, but if one
+                // already did, `.clamp(0, idle_threshold_secs)` below would panic.
+                let idle_threshold_secs = load_idle_threshold_secs(&conn).max(0);
+
                 // Check blacklist
                 if blacklist.iter().any(|b| app_name.to_lowercase().contains(&b.to_lowercase())) {
+                    // Close out whatever was previously focused so the time spent in
+                    // this blacklisted app isn't later added to that row's duration.
+                    if let Some((prev_id, prev_timestamp)) = last_event.take() {
+                        let now = Utc::now();
+                        let elapsed_secs = dwell_secs(prev_timestamp, now, idle_threshold_secs);
+                        conn.execute(
+                            "UPDATE events SET duration_secs = ?1 WHERE id = ?2",
+                            params![elapsed_secs, prev_id],
+                        ).ok();
+                    }
+                    last_app = String::new();
+                    last_title = String::new();
+
+                    drop(conn);
                     thread::sleep(Duration::from_secs(2));
                     continue;
                 }
 
                 if app_name != last_app || title != last_title {
-                    let now = Utc::now().to_rfc3339();
+                    let now = Utc::now();
+
+                    // The window just lost focus: back-fill how long it was actually
+                    // focused, capping at the idle threshold so AFK gaps aren't counted.
+                    if let Some((prev_id, prev_timestamp)) = last_event {
+                        let elapsed_secs = dwell_secs(prev_timestamp, now, idle_threshold_secs);
+                        conn.execute(
+                            "UPDATE events SET duration_secs = ?1 WHERE id = ?2",
+                            params![elapsed_secs, prev_id],
+                        ).ok();
+                    }
 
                     conn.execute(
                         "INSERT INTO events (timestamp, app, title) VALUES (?1, ?2, ?3)",
-                        params![now, app_name, title],
+                        params![now.to_rfc3339(), app_name, title],
                     ).ok();
 
+                    last_event = Some((conn.last_insert_rowid(), now));
                     last_app = app_name;
                     last_title = title;
                 }
+
+                drop(conn);
             }
 
             thread::sleep(Duration::from_secs(2));
@@ -246,10 +356,101 @@ fn parse_date_query(query: &str) -> Option<(String, Option<String>)> {
     None
 }
 
+// Subsequence match score: points per matched char, bonus for consecutive matches
+// and word boundaries. None if query isn't a subsequence of candidate.
+fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let candidate_chars: Vec<char> = candidate.to_lowercase().chars().collect();
+    let query_chars: Vec<char> = query.to_lowercase().chars().collect();
+
+    let mut score: i64 = 0;
+    let mut qi = 0;
+    let mut prev_match_idx: Option<usize> = None;
+
+    for (ci, &c) in candidate_chars.iter().enumerate() {
+        if qi >= query_chars.len() {
+            break;
+        }
+
+        if c == query_chars[qi] {
+            score += 1;
+
+            let at_word_boundary = ci == 0
+                || candidate_chars[ci - 1] == ' '
+                || candidate_chars[ci - 1] == '_'
+                || candidate_chars[ci - 1] == '-'
+                || candidate_chars[ci - 1] == '/';
+            if at_word_boundary {
+                score += 3;
+            }
+
+            if let Some(prev) = prev_match_idx {
+                if ci == prev + 1 {
+                    score += 2;
+                }
+            }
+
+            prev_match_idx = Some(ci);
+            qi += 1;
+        }
+    }
+
+    if qi == query_chars.len() {
+        Some(score)
+    } else {
+        None
+    }
+}
+
+// Quotes a token for safe use in an FTS5 MATCH expression, doubling embedded quotes.
+fn escape_fts_token(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
+
+fn fts_exact_phrase(query: &str) -> String {
+    escape_fts_token(query)
+}
+
+fn fts_prefix_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("{}*", escape_fts_token(term)))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+fn run_fts_query(conn: &Connection, pattern: &str) -> rusqlite::Result<Vec<SearchResult>> {
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.timestamp, e.app, e.title, e.tags
+         FROM events e
+         JOIN events_fts ON events_fts.rowid = e.id
+         WHERE events_fts MATCH ?1
+         ORDER BY e.id DESC
+         LIMIT 100"
+    )?;
+
+    let results = stmt
+        .query_map([pattern], |row| {
+            Ok(SearchResult {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                app: row.get(2)?,
+                title: row.get(3)?,
+                tags: row.get(4)?,
+            })
+        })?
+        .filter_map(Result::ok)
+        .collect();
+
+    Ok(results)
+}
+
 #[tauri::command]
-fn search_memories(app: tauri::AppHandle, query: String) -> Result<Vec<SearchResult>, String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn search_memories(db: tauri::State<Db>, query: String, mode: SearchMode) -> Result<SearchResponse, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let lower = query.to_lowercase();
     let words: Vec<&str> = lower.split_whitespace().collect();
@@ -290,7 +491,7 @@ fn search_memories(app: tauri::AppHandle, query: String) -> Result<Vec<SearchRes
                 .filter_map(Result::ok)
                 .collect();
 
-            return Ok(results);
+            return Ok(SearchResponse { results, warning: None });
         }
     }
 
@@ -347,23 +548,175 @@ fn search_memories(app: tauri::AppHandle, query: String) -> Result<Vec<SearchRes
             .collect()
         };
 
-        return Ok(results);
+        return Ok(SearchResponse { results, warning: None });
     }
 
-    // Use FTS5 for full-text search
-    let pattern = query.split_whitespace().collect::<Vec<_>>().join(" OR ");
-    
-    let mut stmt = conn.prepare(
-        "SELECT e.id, e.timestamp, e.app, e.title, e.tags
-         FROM events e
-         JOIN events_fts ON events_fts.rowid = e.id
-         WHERE events_fts MATCH ?1
-         ORDER BY e.id DESC
-         LIMIT 100"
-    ).map_err(|e| e.to_string())?;
+    // Fuzzy mode ranks recent candidates in Rust rather than going through FTS5.
+    if mode == SearchMode::Fuzzy {
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, app, title, tags
+             FROM events
+             ORDER BY id DESC
+             LIMIT 2000"
+        ).map_err(|e| e.to_string())?;
+
+        let candidates: Vec<SearchResult> = stmt
+            .query_map([], |row| {
+                Ok(SearchResult {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    app: row.get(2)?,
+                    title: row.get(3)?,
+                    tags: row.get(4)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .filter_map(Result::ok)
+            .collect();
+
+        let mut scored: Vec<(i64, SearchResult)> = candidates
+            .into_iter()
+            .filter_map(|r| {
+                let haystack = format!("{} {}", r.app, r.title);
+                fuzzy_score(&haystack, &query).map(|score| (score, r))
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let results = scored.into_iter().take(100).map(|(_, r)| r).collect();
+        return Ok(SearchResponse { results, warning: None });
+    }
+
+    // Exact/Prefix/FullText all go through FTS5, differing only in how the pattern is built.
+    let pattern = match mode {
+        SearchMode::Exact => fts_exact_phrase(&query),
+        SearchMode::Prefix => fts_prefix_query(&query),
+        _ => query
+            .split_whitespace()
+            .map(escape_fts_token)
+            .collect::<Vec<_>>()
+            .join(" OR "),
+    };
+
+    let response = match run_fts_query(&conn, &pattern) {
+        Ok(results) => SearchResponse { results, warning: None },
+        Err(_) => {
+            // Still unparsable (e.g. empty after escaping) — fall back to a literal scan.
+            let like_pattern = format!("%{}%", query);
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, timestamp, app, title, tags
+                     FROM events
+                     WHERE app LIKE ?1 OR title LIKE ?1
+                     ORDER BY id DESC
+                     LIMIT 100"
+                )
+                .map_err(|e| e.to_string())?;
+
+            let results = stmt
+                .query_map([like_pattern], |row| {
+                    Ok(SearchResult {
+                        id: row.get(0)?,
+                        timestamp: row.get(1)?,
+                        app: row.get(2)?,
+                        title: row.get(3)?,
+                        tags: row.get(4)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .filter_map(Result::ok)
+                .collect();
+
+            SearchResponse {
+                results,
+                warning: Some(
+                    "Search query could not be parsed as full-text search; showing literal matches instead.".to_string()
+                ),
+            }
+        }
+    };
+
+    Ok(response)
+}
+
+fn run_filtered_query(conn: &Connection, filters: &SearchFilters) -> rusqlite::Result<Vec<SearchResult>> {
+    // Only take the FTS join when there's an actual term to match: a whitespace-only
+    // title_contains (a bug introduced with this command, fixed in 97d7ce5) would
+    // otherwise tokenize to an empty, invalid FTS5 MATCH pattern.
+    let text_pattern = filters.title_contains.as_deref().and_then(|term| {
+        let pattern = term
+            .split_whitespace()
+            .map(escape_fts_token)
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        if pattern.is_empty() { None } else { Some(pattern) }
+    });
+
+    let mut sql = if text_pattern.is_some() {
+        String::from(
+            "SELECT e.id, e.timestamp, e.app, e.title, e.tags
+             FROM events e
+             JOIN events_fts ON events_fts.rowid = e.id
+             WHERE events_fts MATCH ?"
+        )
+    } else {
+        String::from(
+            "SELECT e.id, e.timestamp, e.app, e.title, e.tags
+             FROM events e
+             WHERE 1=1"
+        )
+    };
+
+    let mut values: Vec<Value> = Vec::new();
+    if let Some(pattern) = text_pattern {
+        values.push(Value::Text(pattern));
+    }
+
+    if let Some(app_name) = &filters.app {
+        sql.push_str(" AND e.app = ?");
+        values.push(Value::Text(app_name.clone()));
+    }
+
+    if let Some(exclude) = &filters.exclude_app {
+        sql.push_str(" AND e.app NOT LIKE ?");
+        values.push(Value::Text(format!("%{}%", exclude)));
+    }
+
+    if let Some(tag) = &filters.tag {
+        sql.push_str(" AND e.tags LIKE ?");
+        values.push(Value::Text(format!("%{}%", tag)));
+    }
+
+    if let Some(after) = &filters.after {
+        sql.push_str(" AND e.timestamp >= ?");
+        values.push(Value::Text(after.clone()));
+    }
+
+    if let Some(before) = &filters.before {
+        sql.push_str(" AND e.timestamp <= ?");
+        values.push(Value::Text(before.clone()));
+    }
+
+    sql.push_str(if filters.reverse {
+        " ORDER BY e.id ASC"
+    } else {
+        " ORDER BY e.id DESC"
+    });
+
+    let limit = filters.limit.unwrap_or(100);
+    sql.push_str(" LIMIT ?");
+    values.push(Value::Integer(limit));
+
+    if let Some(offset) = filters.offset {
+        sql.push_str(" OFFSET ?");
+        values.push(Value::Integer(offset));
+    }
+
+    let mut stmt = conn.prepare(&sql)?;
 
     let results = stmt
-        .query_map([pattern], |row| {
+        .query_map(params_from_iter(values), |row| {
             Ok(SearchResult {
                 id: row.get(0)?,
                 timestamp: row.get(1)?,
@@ -371,8 +724,7 @@ fn search_memories(app: tauri::AppHandle, query: String) -> Result<Vec<SearchRes
                 title: row.get(3)?,
                 tags: row.get(4)?,
             })
-        })
-        .map_err(|e| e.to_string())?
+        })?
         .filter_map(Result::ok)
         .collect();
 
@@ -380,9 +732,14 @@ fn search_memories(app: tauri::AppHandle, query: String) -> Result<Vec<SearchRes
 }
 
 #[tauri::command]
-fn get_statistics(app: tauri::AppHandle) -> Result<Vec<AppStats>, String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn search_filtered(db: tauri::State<Db>, filters: SearchFilters) -> Result<Vec<SearchResult>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    run_filtered_query(&conn, &filters).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_statistics(db: tauri::State<Db>) -> Result<Vec<AppStats>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn.prepare(
         "SELECT app, COUNT(*) as count, MIN(timestamp) as first_seen, MAX(timestamp) as last_seen
@@ -408,9 +765,36 @@ fn get_statistics(app: tauri::AppHandle) -> Result<Vec<AppStats>, String> {
     Ok(results)
 }
 
+// Total focused time per app within [start, end].
+#[tauri::command]
+fn get_time_breakdown(db: tauri::State<Db>, start: String, end: String) -> Result<Vec<TimeBreakdown>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT app, COALESCE(SUM(duration_secs), 0) as total_secs
+         FROM events
+         WHERE timestamp >= ?1 AND timestamp <= ?2
+         GROUP BY app
+         ORDER BY total_secs DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let results = stmt
+        .query_map(params![start, end], |row| {
+            Ok(TimeBreakdown {
+                app: row.get(0)?,
+                total_secs: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .collect();
+
+    Ok(results)
+}
+
 #[tauri::command]
-fn export_to_csv(app: tauri::AppHandle, query: String) -> Result<String, String> {
-    let results = search_memories(app, query)?;
+fn export_to_csv(db: tauri::State<Db>, query: String) -> Result<String, String> {
+    let results = search_memories(db, query, SearchMode::FullText)?.results;
     
     let mut wtr = csv::Writer::from_writer(vec![]);
     wtr.write_record(&["ID", "Timestamp", "App", "Title", "Tags"])
@@ -434,9 +818,8 @@ fn export_to_csv(app: tauri::AppHandle, query: String) -> Result<String, String>
 }
 
 #[tauri::command]
-fn add_tag(app: tauri::AppHandle, event_id: i64, tag: String) -> Result<(), String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn add_tag(db: tauri::State<Db>, event_id: i64, tag: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     conn.execute(
         "UPDATE events SET tags = ?1 WHERE id = ?2",
@@ -447,16 +830,14 @@ fn add_tag(app: tauri::AppHandle, event_id: i64, tag: String) -> Result<(), Stri
 }
 
 #[tauri::command]
-fn get_blacklist(app: tauri::AppHandle) -> Result<Vec<String>, String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn get_blacklist(db: tauri::State<Db>) -> Result<Vec<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
     Ok(load_blacklist(&conn))
 }
 
 #[tauri::command]
-fn update_blacklist(app: tauri::AppHandle, blacklist: Vec<String>) -> Result<(), String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn update_blacklist(db: tauri::State<Db>, blacklist: Vec<String>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let json = serde_json::to_string(&blacklist).map_err(|e| e.to_string())?;
 
@@ -469,9 +850,30 @@ fn update_blacklist(app: tauri::AppHandle, blacklist: Vec<String>) -> Result<(),
 }
 
 #[tauri::command]
-fn get_recent_searches(app: tauri::AppHandle) -> Result<Vec<String>, String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn get_idle_threshold(db: tauri::State<Db>) -> Result<i64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_idle_threshold_secs(&conn))
+}
+
+#[tauri::command]
+fn update_idle_threshold(db: tauri::State<Db>, idle_threshold_secs: i64) -> Result<(), String> {
+    if idle_threshold_secs < 0 {
+        return Err("idle_threshold_secs must not be negative".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO config (key, value) VALUES ('idle_threshold_secs', ?1)",
+        [idle_threshold_secs.to_string()],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_recent_searches(db: tauri::State<Db>) -> Result<Vec<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let result: Result<String, _> = conn.query_row(
         "SELECT value FROM config WHERE key = 'recent_searches'",
@@ -486,12 +888,9 @@ fn get_recent_searches(app: tauri::AppHandle) -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-fn save_recent_search(app: tauri::AppHandle, query: String) -> Result<(), String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn save_recent_search(db: tauri::State<Db>, query: String) -> Result<(), String> {
+    let mut recent: Vec<String> = get_recent_searches(db.clone()).unwrap_or_default();
 
-    let mut recent: Vec<String> = get_recent_searches(app.clone()).unwrap_or_default();
-    
     // Remove if exists and add to front
     recent.retain(|q| q != &query);
     recent.insert(0, query);
@@ -499,6 +898,8 @@ fn save_recent_search(app: tauri::AppHandle, query: String) -> Result<(), String
 
     let json = serde_json::to_string(&recent).map_err(|e| e.to_string())?;
 
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
     conn.execute(
         "INSERT OR REPLACE INTO config (key, value) VALUES ('recent_searches', ?1)",
         [json],
@@ -508,9 +909,8 @@ fn save_recent_search(app: tauri::AppHandle, query: String) -> Result<(), String
 }
 
 #[tauri::command]
-fn get_timeline(app: tauri::AppHandle, date: String) -> Result<Vec<SearchResult>, String> {
-    let path = get_db_path(&app).map_err(|e| e.to_string())?;
-    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+fn get_timeline(db: tauri::State<Db>, date: String) -> Result<Vec<SearchResult>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let start = format!("{}T00:00:00", date);
     let end = format!("{}T23:59:59", date);
@@ -539,21 +939,204 @@ fn get_timeline(app: tauri::AppHandle, date: String) -> Result<Vec<SearchResult>
     Ok(results)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dwell_secs_returns_elapsed_time_under_threshold() {
+        let prev = Utc::now();
+        let now = prev + chrono::Duration::seconds(30);
+        assert_eq!(dwell_secs(prev, now, 300), 30);
+    }
+
+    #[test]
+    fn dwell_secs_caps_at_idle_threshold() {
+        let prev = Utc::now();
+        let now = prev + chrono::Duration::seconds(600);
+        assert_eq!(dwell_secs(prev, now, 300), 300);
+    }
+
+    #[test]
+    fn dwell_secs_floors_at_zero_for_clock_skew() {
+        let now = Utc::now();
+        let prev = now + chrono::Duration::seconds(5);
+        assert_eq!(dwell_secs(prev, now, 300), 0);
+    }
+
+    #[test]
+    fn escape_fts_token_doubles_embedded_quotes() {
+        assert_eq!(escape_fts_token("foo"), "\"foo\"");
+        assert_eq!(escape_fts_token("foo\"bar"), "\"foo\"\"bar\"");
+    }
+
+    #[test]
+    fn escape_fts_token_neutralizes_fts5_operators() {
+        // These all have special meaning to the FTS5 query syntax; quoting must
+        // turn every one of them into an inert literal.
+        for term in ["foo:bar", "NOT", "AND", "OR", "(foo)", "foo*"] {
+            let escaped = escape_fts_token(term);
+            assert!(escaped.starts_with('"') && escaped.ends_with('"'));
+        }
+    }
+
+    #[test]
+    fn fts_exact_phrase_wraps_whole_query() {
+        assert_eq!(fts_exact_phrase("hello world"), "\"hello world\"");
+        assert_eq!(fts_exact_phrase("foo\"bar"), "\"foo\"\"bar\"");
+    }
+
+    #[test]
+    fn fts_prefix_query_ands_escaped_prefix_terms() {
+        assert_eq!(fts_prefix_query("foo bar"), "\"foo\"* AND \"bar\"*");
+        assert_eq!(fts_prefix_query("foo:bar"), "\"foo:bar\"*");
+    }
+
+    #[test]
+    fn problem_titles_produce_valid_fts5_queries() {
+        // A title containing any of these shouldn't crash search with an
+        // `fts5: syntax error` once run through the escaping helpers.
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute(
+            "INSERT INTO events (timestamp, app, title) VALUES ('2024-01-01T00:00:00Z', 'Terminal', 'foo:bar')",
+            [],
+        ).unwrap();
+
+        for title in ["foo:bar", "say \"hi\"", "NOT todo", "AND then", "(parens)"] {
+            let pattern = fts_exact_phrase(title);
+            run_fts_query(&conn, &pattern).unwrap();
+
+            let prefix_pattern = fts_prefix_query(title);
+            run_fts_query(&conn, &prefix_pattern).unwrap();
+        }
+    }
+
+    #[test]
+    fn fuzzy_score_requires_in_order_subsequence() {
+        assert_eq!(fuzzy_score("visual studio code", ""), Some(0));
+        assert!(fuzzy_score("visual studio code", "vsc").is_some());
+        assert!(fuzzy_score("visual studio code", "xyz").is_none());
+    }
+
+    #[test]
+    fn fuzzy_score_rewards_consecutive_and_word_boundary_matches() {
+        // "vs" is a consecutive, word-boundary-starting match in "vs code" but
+        // a scattered, mid-word match in "advisors"; the former should score higher.
+        let boundary_score = fuzzy_score("vs code", "vs").unwrap();
+        let scattered_score = fuzzy_score("advisors", "vs").unwrap();
+        assert!(boundary_score > scattered_score);
+    }
+
+    #[test]
+    fn run_migrations_is_idempotent_and_versions_the_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.last().unwrap().version);
+
+        // duration_secs was added by migration 2.
+        conn.execute(
+            "INSERT INTO events (timestamp, app, title, duration_secs) VALUES ('2024-01-01T00:00:00Z', 'Terminal', 'hi', 5)",
+            [],
+        ).unwrap();
+
+        // Re-running should be a no-op, not re-apply (and fail on) already-applied migrations.
+        run_migrations(&mut conn).unwrap();
+        let version_after: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version_after, version);
+    }
+
+    fn seeded_conn() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        for (timestamp, app, title) in [
+            ("2024-01-01T00:00:00Z", "Terminal", "build project"),
+            ("2024-01-02T00:00:00Z", "Browser", "read docs"),
+            ("2024-01-03T00:00:00Z", "Terminal", "run tests"),
+            ("2024-01-04T00:00:00Z", "Editor", "write docs"),
+        ] {
+            conn.execute(
+                "INSERT INTO events (timestamp, app, title) VALUES (?1, ?2, ?3)",
+                params![timestamp, app, title],
+            ).unwrap();
+        }
+
+        conn
+    }
+
+    #[test]
+    fn search_filtered_combines_app_and_after_and_reverses_order() {
+        let conn = seeded_conn();
+        let filters = SearchFilters {
+            app: Some("Terminal".to_string()),
+            after: Some("2024-01-01T12:00:00Z".to_string()),
+            reverse: true,
+            ..Default::default()
+        };
+
+        let results = run_filtered_query(&conn, &filters).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "run tests");
+
+        // Without the after filter both Terminal rows match; reverse returns them oldest first.
+        let filters = SearchFilters {
+            app: Some("Terminal".to_string()),
+            reverse: true,
+            ..Default::default()
+        };
+        let titles: Vec<String> = run_filtered_query(&conn, &filters)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.title)
+            .collect();
+        assert_eq!(titles, vec!["build project", "run tests"]);
+    }
+
+    #[test]
+    fn search_filtered_matches_title_contains_via_fts() {
+        let conn = seeded_conn();
+        let filters = SearchFilters {
+            title_contains: Some("docs".to_string()),
+            ..Default::default()
+        };
+
+        let mut results = run_filtered_query(&conn, &filters).unwrap();
+        results.sort_by_key(|r| r.id);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "read docs");
+        assert_eq!(results[1].title, "write docs");
+    }
+}
+
 fn main() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             search_memories,
+            search_filtered,
             get_statistics,
+            get_time_breakdown,
             export_to_csv,
             add_tag,
             get_blacklist,
             update_blacklist,
+            get_idle_threshold,
+            update_idle_threshold,
             get_recent_searches,
             save_recent_search,
             get_timeline
         ])
         .setup(|app| {
-            start_logger(app.handle().clone());
+            let handle = app.handle().clone();
+            let mut conn = open_connection(&handle)?;
+            run_migrations(&mut conn)?;
+            app.manage(Db(Mutex::new(conn)));
+
+            start_logger(handle);
             Ok(())
         })
         .run(tauri::generate_context!())